@@ -1,5 +1,9 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
 use std::fmt;
 
+use parser::ast::Ident;
+
 use crate::{
     expression::{Expr, Resolved},
     state::{ExternalEnv, LocalEnv},
@@ -18,16 +22,28 @@ pub struct Block {
     pub(crate) local_env: LocalEnv,
     selection_vector_this: Vec<usize>,
     selection_vector_other: Vec<usize>,
+
+    /// The expressions actually evaluated at runtime: a clone of `inner`
+    /// that `eliminate_dead_stores` prunes in place the first time
+    /// `type_def` runs. Kept separate from `inner` (which stays the
+    /// original, un-pruned source) so `into_inner`/`Display` keep
+    /// reflecting what the user wrote.
+    live: RefCell<Vec<Expr>>,
+    dead_stores_eliminated: Cell<bool>,
 }
 
 impl Block {
     #[must_use]
     pub fn new(inner: Vec<Expr>, local_env: LocalEnv) -> Self {
+        let live = RefCell::new(inner.clone());
+
         Self {
             inner,
             local_env,
             selection_vector_this: vec![],
             selection_vector_other: vec![],
+            live,
+            dead_stores_eliminated: Cell::new(false),
         }
     }
 
@@ -35,6 +51,178 @@ impl Block {
     pub fn into_inner(self) -> Vec<Expr> {
         self.inner
     }
+
+    /// Drops assignments from the runtime expression list whose target
+    /// variable is never read again, once the assigned value is cheap
+    /// enough to discard safely. Runs at most once, triggered by the
+    /// first `type_def` call (i.e. once type-checking has happened).
+    ///
+    /// This is a backward liveness pass: walk the expressions in reverse
+    /// execution order, tracking which variables are live, treating a read
+    /// as making its variable live and an assignment as making its
+    /// variable dead again. An assignment found dead at that point is
+    /// elided only when its right-hand side can't fail, abort, or
+    /// terminate the program — see `StoreFact::safe_to_drop_if_dead` and
+    /// `compute_keep_mask`, which implement this independent of `Expr`.
+    ///
+    /// `parent_env` is the local environment this block was entered with.
+    /// VRL blocks share a flat runtime scope with their parent (see the
+    /// NOTE in `resolve()`), so a variable this block assigns that was
+    /// already bound in `parent_env` may still be read by sibling
+    /// expressions after this block ends, and must be seeded as
+    /// live-on-exit.
+    ///
+    /// The block's own tail expression is always treated as live,
+    /// regardless of `parent_env` or what's read below: `resolve()` and
+    /// `type_def()` both use it as the block's result, so eliding it would
+    /// either panic (if it were the only expression) or silently change
+    /// the block's value/type. This is what makes a bare `x = 1` (with `x`
+    /// not already bound in `parent_env`) safe as the sole statement of an
+    /// `if`/`else` branch whose result is read afterwards.
+    fn eliminate_dead_stores(&self, parent_env: &LocalEnv, external: &ExternalEnv) {
+        if self.dead_stores_eliminated.replace(true) {
+            return;
+        }
+
+        let facts: Vec<StoreFact<<Expr as VariableEffects>::Ident>> = self
+            .inner
+            .iter()
+            .map(|expr| {
+                let assigns = expr.assigns();
+                let safe_to_drop_if_dead = assigns.is_some() && {
+                    let type_def = expr.type_def((&self.local_env, external));
+                    !type_def.is_fallible() && !type_def.is_abortable() && !type_def.is_never()
+                };
+
+                StoreFact {
+                    reads: expr.reads(),
+                    reads_everything: expr.reads_everything(),
+                    assigns,
+                    safe_to_drop_if_dead,
+                }
+            })
+            .collect();
+
+        let live_on_exit: HashSet<_> = facts
+            .iter()
+            .filter_map(|fact| fact.assigns.clone())
+            .filter(|ident| parent_env.variable(ident).is_some())
+            .collect();
+
+        let keep = compute_keep_mask(&facts, &live_on_exit);
+
+        let mut keep = keep.into_iter();
+        self.live.borrow_mut().retain(|_| keep.next().unwrap_or(true));
+    }
+}
+
+/// What the dead-store pass needs to know about one expression's effect on
+/// local variables: which ones it reads, and which one (if any) it
+/// assigns. `Expr` implements this for its `Variable`, `Literal`, `Noop`
+/// and `Assignment` variants; anything else conservatively reports
+/// `reads_everything` instead of trying to track its sub-expressions
+/// precisely.
+pub(crate) trait VariableEffects {
+    type Ident: Eq + std::hash::Hash + Clone;
+
+    fn reads(&self) -> Vec<Self::Ident>;
+    fn assigns(&self) -> Option<Self::Ident>;
+
+    /// True for expressions whose read set this trait can't enumerate
+    /// precisely (anything but a bare variable reference, a literal, a
+    /// no-op, or an assignment of one of these). `compute_keep_mask` treats such an expression as reading
+    /// every variable still live at that point, so a store is only ever
+    /// elided when we're actually sure nothing after it could read it.
+    fn reads_everything(&self) -> bool {
+        false
+    }
+}
+
+impl VariableEffects for Expr {
+    type Ident = Ident;
+
+    fn reads(&self) -> Vec<Ident> {
+        match self {
+            Expr::Variable(variable) => vec![variable.ident().clone()],
+            Expr::Assignment(assignment) => assignment.value().reads(),
+            _ => vec![],
+        }
+    }
+
+    fn assigns(&self) -> Option<Ident> {
+        match self {
+            Expr::Assignment(assignment) => assignment.target_ident().cloned(),
+            _ => None,
+        }
+    }
+
+    fn reads_everything(&self) -> bool {
+        match self {
+            Expr::Variable(_) | Expr::Literal(_) | Expr::Noop(_) => false,
+            // This trait is only about *local variable* reads, so an
+            // assignment is exactly as opaque as its value, regardless of
+            // whether its target is a local variable or an external path.
+            // A path target (e.g. `.out = ...`) doesn't read any local
+            // variable by virtue of being a path — only what its value
+            // expression reads matters, same as a variable target.
+            Expr::Assignment(assignment) => assignment.value().reads_everything(),
+            _ => true,
+        }
+    }
+}
+
+/// A single expression's effect on variable liveness, computed up front so
+/// the dataflow walk in `compute_keep_mask` stays pure and independently
+/// testable, without needing a real `Expr`/`LocalEnv`.
+struct StoreFact<Ident> {
+    reads: Vec<Ident>,
+    reads_everything: bool,
+    assigns: Option<Ident>,
+    safe_to_drop_if_dead: bool,
+}
+
+/// Runs the backward liveness walk described on `eliminate_dead_stores`
+/// over a sequence of `StoreFact`s, returning which indices to keep.
+/// `live_on_exit` seeds the live set with variables that must survive the
+/// walk regardless of whether anything in `facts` reads them (a variable
+/// bound in the parent scope, or anything read after the block boundary).
+///
+/// The last fact is always kept: it's the block's tail expression, whose
+/// value `resolve()`/`type_def()` use as the block's own result regardless
+/// of whether its assigned variable (if any) is read by anything else.
+fn compute_keep_mask<Ident>(facts: &[StoreFact<Ident>], live_on_exit: &HashSet<Ident>) -> Vec<bool>
+where
+    Ident: Eq + std::hash::Hash + Clone,
+{
+    let mut live = live_on_exit.clone();
+    let mut keep = vec![true; facts.len()];
+    // Once we've passed (walking backward) an expression we can't analyze
+    // precisely, every earlier store might be read by it, so nothing before
+    // that point can be proven dead.
+    let mut opaque_from_here = false;
+
+    for index in (0..facts.len()).rev() {
+        let fact = &facts[index];
+        live.extend(fact.reads.iter().cloned());
+
+        if let Some(ident) = &fact.assigns {
+            let is_tail = index == facts.len() - 1;
+            let provably_dead = !is_tail && !opaque_from_here && !live.contains(ident);
+            if provably_dead && fact.safe_to_drop_if_dead {
+                keep[index] = false;
+            }
+            live.remove(ident);
+        }
+
+        // Applied after judging this fact's own store: an assignment whose
+        // value isn't precisely trackable (e.g. `x = parse_json!(...)`) is
+        // opaque about what it reads, not about whether its own target is
+        // dead — that's still decided by what's live *after* it. The opacity
+        // only needs to block provably-dead judgements on *earlier* stores.
+        opaque_from_here |= fact.reads_everything;
+    }
+
+    keep
 }
 
 impl Expression for Block {
@@ -50,7 +238,8 @@ impl Expression for Block {
         //
         // This also means we don't need to make any changes to the VM runtime,
         // as it uses the same compiler as this AST runtime.
-        let (last, other) = self.inner.split_last().expect("at least one expression");
+        let live = self.live.borrow();
+        let (last, other) = live.split_last().expect("at least one expression");
 
         other
             .iter()
@@ -60,13 +249,15 @@ impl Expression for Block {
     }
 
     fn resolve_batch(&mut self, ctx: &mut BatchContext, selection_vector: &[usize]) {
-        if self.inner.len() == 1 {
-            self.inner[0].resolve_batch(ctx, selection_vector);
+        let live = self.live.get_mut();
+
+        if live.len() == 1 {
+            live[0].resolve_batch(ctx, selection_vector);
         } else {
             self.selection_vector_this.resize(selection_vector.len(), 0);
             self.selection_vector_this.copy_from_slice(selection_vector);
 
-            for block in &mut self.inner {
+            for block in live.iter_mut() {
                 block.resolve_batch(ctx, &self.selection_vector_this);
                 self.selection_vector_other.truncate(0);
 
@@ -97,12 +288,14 @@ impl Expression for Block {
     ///
     /// VRL is allowed to have expressions after a terminating expression, but the compiler
     /// MUST not include them in a block expression when compiled.
-    fn type_def(&self, (_, external): (&LocalEnv, &ExternalEnv)) -> TypeDef {
+    fn type_def(&self, (parent_env, external): (&LocalEnv, &ExternalEnv)) -> TypeDef {
+        self.eliminate_dead_stores(parent_env, external);
+
         let mut last = TypeDef::null();
         let mut fallible = false;
         let mut abortable = false;
         let mut has_terminated = false;
-        for expr in &self.inner {
+        for expr in self.live.borrow().iter() {
             assert!(!has_terminated, "VRL block contains an expression after a terminating expression. This is an internal compiler error. Please submit a bug report.");
             last = expr.type_def((&self.local_env, external));
             if last.is_never() {
@@ -131,7 +324,7 @@ impl Expression for Block {
         ctx.build_unconditional_branch(block_begin_block);
         ctx.position_at_end(block_begin_block);
 
-        for expr in &self.inner {
+        for expr in self.live.borrow().iter() {
             let type_def = expr.type_def(state);
             if type_def.is_fallible() {
                 ctx.emit_llvm_for_ref(expr, state, ctx.result_ref())?;
@@ -177,3 +370,148 @@ impl fmt::Display for Block {
         f.write_str("\n}")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fact(reads: &[u32], assigns: Option<u32>, safe_to_drop_if_dead: bool) -> StoreFact<u32> {
+        StoreFact {
+            reads: reads.to_vec(),
+            reads_everything: false,
+            assigns,
+            safe_to_drop_if_dead,
+        }
+    }
+
+    fn opaque_fact() -> StoreFact<u32> {
+        StoreFact {
+            reads: vec![],
+            reads_everything: true,
+            assigns: None,
+            safe_to_drop_if_dead: false,
+        }
+    }
+
+    #[test]
+    fn elides_a_pure_store_that_is_never_read_again() {
+        let facts = vec![
+            fact(&[], Some(1), true), // x = 1; (dead: never read below)
+            fact(&[], None, false),   // some unrelated trailing expression
+        ];
+
+        assert_eq!(compute_keep_mask(&facts, &HashSet::new()), vec![false, true]);
+    }
+
+    #[test]
+    fn keeps_a_store_that_is_read_later() {
+        let facts = vec![
+            fact(&[], Some(1), true), // x = 1;
+            fact(&[1], None, false),  // ...; x
+        ];
+
+        assert_eq!(compute_keep_mask(&facts, &HashSet::new()), vec![true, true]);
+    }
+
+    #[test]
+    fn keeps_a_dead_store_that_is_not_pure() {
+        let facts = vec![
+            fact(&[], Some(1), false), // x = fallible_call(); (dead, but not safe to drop)
+            fact(&[], None, false),
+        ];
+
+        assert_eq!(compute_keep_mask(&facts, &HashSet::new()), vec![true, true]);
+    }
+
+    #[test]
+    fn keeps_a_store_live_on_exit() {
+        let facts = vec![
+            fact(&[], Some(1), true), // x = 1; captured by a closure
+            fact(&[], None, false),   // some unrelated trailing expression
+        ];
+
+        let live_on_exit = HashSet::from([1]);
+        assert_eq!(compute_keep_mask(&facts, &live_on_exit), vec![true, true]);
+    }
+
+    #[test]
+    fn elides_multiple_dead_stores_in_one_pass() {
+        let facts = vec![
+            fact(&[], Some(1), true), // a = 1; (dead)
+            fact(&[], Some(2), true), // b = 2; (dead)
+            fact(&[], None, false),   // true
+        ];
+
+        assert_eq!(
+            compute_keep_mask(&facts, &HashSet::new()),
+            vec![false, false, true]
+        );
+    }
+
+    #[test]
+    fn elides_a_dead_store_whose_own_value_reads_everything() {
+        let facts = vec![
+            StoreFact {
+                reads: vec![],
+                reads_everything: true, // x = upcase("a"); (dead: never read below)
+                assigns: Some(1),
+                safe_to_drop_if_dead: true,
+            },
+            fact(&[], None, false), // some unrelated trailing expression
+        ];
+
+        assert_eq!(compute_keep_mask(&facts, &HashSet::new()), vec![false, true]);
+    }
+
+    #[test]
+    fn keeps_a_dead_store_that_is_the_blocks_only_statement() {
+        // `x = 1`, as the sole statement of an `if`/`else` branch whose
+        // result (`x`) is read after the block ends. Nothing inside the
+        // block reads `x` and it isn't bound in `parent_env`, so without
+        // always protecting the tail this would be (wrongly) elided —
+        // and, being the block's only expression, would panic `resolve()`.
+        let facts = vec![fact(&[], Some(1), true)];
+
+        assert_eq!(compute_keep_mask(&facts, &HashSet::new()), vec![true]);
+    }
+
+    #[test]
+    fn keeps_a_dead_tail_store_after_other_dead_stores() {
+        // `a = 1; x = 2`, inside a branch whose result (`x`) is read
+        // afterwards; `a` is genuinely unused and still gets elided.
+        let facts = vec![
+            fact(&[], Some(11), true), // a = 1; (dead, elided)
+            fact(&[], Some(1), true),  // x = 2; (tail — always kept)
+        ];
+
+        assert_eq!(compute_keep_mask(&facts, &HashSet::new()), vec![false, true]);
+    }
+
+    #[test]
+    fn keeps_a_store_before_an_opaque_expression() {
+        let facts = vec![
+            fact(&[], Some(1), true), // x = 1;
+            opaque_fact(),            // some_fn_call(); (might read x, we can't tell)
+        ];
+
+        assert_eq!(compute_keep_mask(&facts, &HashSet::new()), vec![true, true]);
+    }
+
+    #[test]
+    fn elides_a_scratch_var_before_a_trailing_output_path_assignment() {
+        // `tmp = upcase("a"); .out = "fixed"` — the realistic
+        // "scratch variable feeding an output field" pattern this pass
+        // exists for. The trailing statement assigns to a path (not a
+        // local variable, so `fact.assigns` is `None` here), but its value
+        // is a plain literal, so `Expr::reads_everything` (see
+        // `impl VariableEffects for Expr`) now correctly reports `false`
+        // for it instead of unconditionally poisoning everything before it
+        // just because the assignment target isn't a local variable.
+        let facts = vec![
+            fact(&[], Some(1), true), // tmp = upcase("a"); (dead)
+            fact(&[], None, false),   // .out = "fixed"; (reads_everything: false)
+        ];
+
+        assert_eq!(compute_keep_mask(&facts, &HashSet::new()), vec![false, true]);
+    }
+}
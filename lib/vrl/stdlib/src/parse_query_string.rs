@@ -1,4 +1,6 @@
-use super::url_util::{parse_query, query_inner_kind};
+use std::collections::BTreeMap;
+
+use super::url_util::{parse_query, query_inner_kind, Conversion};
 
 use url::form_urlencoded;
 use vrl::prelude::*;
@@ -12,16 +14,38 @@ impl Function for ParseQueryString {
     }
 
     fn examples(&self) -> &'static [Example] {
-        &[Example {
-            title: "parse query string",
-            source: r#"parse_query_string("foo=1&bar=2")"#,
-            result: Ok(r#"
-                {
-                    "foo": "1",
-                    "bar": "2"
-                }
-            "#),
-        }]
+        &[
+            Example {
+                title: "parse query string",
+                source: r#"parse_query_string("foo=1&bar=2")"#,
+                result: Ok(r#"
+                    {
+                        "foo": "1",
+                        "bar": "2"
+                    }
+                "#),
+            },
+            Example {
+                title: "parse query string with types",
+                source: r#"parse_query_string("foo=1&bar=true", types: {"foo": "int", "bar": "bool"})"#,
+                result: Ok(r#"
+                    {
+                        "foo": 1,
+                        "bar": true
+                    }
+                "#),
+            },
+            Example {
+                title: "parse query string with nested fields",
+                source: r#"parse_query_string("foo[]=1&foo[]=2&user[name]=bob", nested: true)"#,
+                result: Ok(r#"
+                    {
+                        "foo": ["1", "2"],
+                        "user": { "name": "bob" }
+                    }
+                "#),
+            },
+        ]
     }
 
     fn compile(
@@ -31,21 +55,77 @@ impl Function for ParseQueryString {
         mut arguments: ArgumentList,
     ) -> Compiled {
         let value = arguments.required("value");
-        Ok(Box::new(ParseQueryStringFn { value }))
+        let types = arguments
+            .optional("types")
+            .map(|expr| {
+                let object = expr
+                    .as_value()
+                    .ok_or(Error::TypesArgumentNotLiteral)?
+                    .try_object()
+                    .map_err(|_| Error::TypesArgumentNotLiteral)?;
+
+                object
+                    .into_iter()
+                    .map(|(field, spec)| {
+                        let spec = spec
+                            .try_bytes_utf8_lossy()
+                            .map_err(|_| Error::TypesArgumentNotLiteral)?;
+
+                        spec.parse::<Conversion>()
+                            .map(|conversion| (field.to_string(), conversion))
+                            .map_err(|error| Error::InvalidConversion {
+                                field: field.to_string(),
+                                error,
+                            })
+                    })
+                    .collect::<Result<BTreeMap<_, _>, Error>>()
+            })
+            .transpose()?
+            .unwrap_or_default();
+        let nested = arguments
+            .optional("nested")
+            .map(|expr| {
+                expr.as_value()
+                    .ok_or(Error::NestedArgumentNotLiteral)?
+                    .try_boolean()
+                    .map_err(|_| Error::NestedArgumentNotLiteral)
+            })
+            .transpose()?
+            .unwrap_or(false);
+
+        Ok(Box::new(ParseQueryStringFn {
+            value,
+            types,
+            nested,
+        }))
     }
 
     fn parameters(&self) -> &'static [Parameter] {
-        &[Parameter {
-            keyword: "value",
-            kind: kind::BYTES,
-            required: true,
-        }]
+        &[
+            Parameter {
+                keyword: "value",
+                kind: kind::BYTES,
+                required: true,
+            },
+            Parameter {
+                keyword: "types",
+                kind: kind::OBJECT,
+                required: false,
+            },
+            Parameter {
+                keyword: "nested",
+                kind: kind::BOOLEAN,
+                required: false,
+            },
+        ]
     }
 }
 
 #[derive(Debug, Clone)]
 struct ParseQueryStringFn {
     value: Box<dyn Expression>,
+    types: BTreeMap<String, Conversion>,
+    nested: bool,
 }
 
 impl Expression for ParseQueryStringFn {
@@ -58,13 +138,36 @@ impl Expression for ParseQueryStringFn {
         }
 
         let query = form_urlencoded::parse(query_string);
-        let result = parse_query(query);
+        let result = parse_query(query, &self.types, self.nested)?;
 
         Ok(result.into())
     }
 
     fn type_def(&self, _: &state::Compiler) -> TypeDef {
-        TypeDef::object(query_inner_kind())
+        TypeDef::object(query_inner_kind(&self.types, self.nested))
+            .with_fallibility(!self.types.is_empty())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum Error {
+    #[error("types argument must be a static object mapping field names to conversions")]
+    TypesArgumentNotLiteral,
+
+    #[error("nested argument must be a static boolean literal")]
+    NestedArgumentNotLiteral,
+
+    #[error("invalid conversion for field `{field}`: {error}")]
+    InvalidConversion { field: String, error: String },
+}
+
+impl DiagnosticMessage for Error {
+    fn code(&self) -> usize {
+        900
+    }
+
+    fn labels(&self) -> Vec<Label> {
+        vec![]
     }
 }
 
@@ -72,6 +175,13 @@ impl Expression for ParseQueryStringFn {
 mod tests {
     use super::*;
 
+    fn test_types(pairs: &[(&str, &str)]) -> BTreeMap<String, Conversion> {
+        pairs
+            .iter()
+            .map(|(field, spec)| (field.to_string(), spec.parse().unwrap()))
+            .collect()
+    }
+
     test_function![
         parse_query_string => ParseQueryString;
 
@@ -83,7 +193,7 @@ mod tests {
                 xyz: "",
                 abc: "",
             })),
-            tdef: TypeDef::object(query_inner_kind()),
+            tdef: TypeDef::object(query_inner_kind(&BTreeMap::new(), false)),
         }
 
         multiple_values {
@@ -91,7 +201,7 @@ mod tests {
             want: Ok(value!({
                 foo: ["bar", "xyz"],
             })),
-            tdef: TypeDef::object(query_inner_kind()),
+            tdef: TypeDef::object(query_inner_kind(&BTreeMap::new(), false)),
         }
 
         ruby_on_rails_multiple_values {
@@ -99,7 +209,7 @@ mod tests {
             want: Ok(value!({
                 "foo[]": ["bar", "xyz"],
             })),
-            tdef: TypeDef::object(query_inner_kind()),
+            tdef: TypeDef::object(query_inner_kind(&BTreeMap::new(), false)),
         }
 
         empty_key {
@@ -107,7 +217,7 @@ mod tests {
             want: Ok(value!({
                 "": ["", ""],
             })),
-            tdef: TypeDef::object(query_inner_kind()),
+            tdef: TypeDef::object(query_inner_kind(&BTreeMap::new(), false)),
         }
 
         single_key {
@@ -115,13 +225,13 @@ mod tests {
             want: Ok(value!({
                 foo: "",
             })),
-            tdef: TypeDef::object(query_inner_kind()),
+            tdef: TypeDef::object(query_inner_kind(&BTreeMap::new(), false)),
         }
 
         empty {
             args: func_args![value: value!("")],
             want: Ok(value!({})),
-            tdef: TypeDef::object(query_inner_kind()),
+            tdef: TypeDef::object(query_inner_kind(&BTreeMap::new(), false)),
         }
 
         starts_with_question_mark {
@@ -129,7 +239,105 @@ mod tests {
             want: Ok(value!({
                 foo: "bar",
             })),
-            tdef: TypeDef::object(query_inner_kind()),
+            tdef: TypeDef::object(query_inner_kind(&BTreeMap::new(), false)),
+        }
+
+        with_types {
+            args: func_args![
+                value: value!("id=1&score=1.5&active=true&name=bob"),
+                types: value!({
+                    id: "int",
+                    score: "float",
+                    active: "bool",
+                }),
+            ],
+            want: Ok(value!({
+                id: 1,
+                score: 1.5,
+                active: true,
+                name: "bob",
+            })),
+            tdef: TypeDef::object(query_inner_kind(&test_types(&[("id", "int"), ("score", "float"), ("active", "bool")]), false)).with_fallibility(true),
+        }
+
+        with_types_conversion_error {
+            args: func_args![
+                value: value!("id=not-a-number"),
+                types: value!({ id: "int" }),
+            ],
+            want: Err("unable to coerce field `id` to `integer`: invalid digit found in string"),
+            tdef: TypeDef::object(query_inner_kind(&test_types(&[("id", "int")]), false)).with_fallibility(true),
+        }
+
+        nested_array {
+            args: func_args![value: value!("foo[]=a&foo[]=b"), nested: true],
+            want: Ok(value!({
+                foo: ["a", "b"],
+            })),
+            tdef: TypeDef::object(query_inner_kind(&BTreeMap::new(), true)),
+        }
+
+        nested_object {
+            args: func_args![value: value!("user[name]=x&user[age]=3"), nested: true],
+            want: Ok(value!({
+                user: { name: "x", age: "3" },
+            })),
+            tdef: TypeDef::object(query_inner_kind(&BTreeMap::new(), true)),
+        }
+
+        nested_array_inside_object {
+            args: func_args![value: value!("a[b][]=1&a[b][]=2"), nested: true],
+            want: Ok(value!({
+                a: { b: ["1", "2"] },
+            })),
+            tdef: TypeDef::object(query_inner_kind(&BTreeMap::new(), true)),
+        }
+
+        nested_malformed_key_falls_back_to_literal {
+            args: func_args![value: value!("foo[bar=1"), nested: true],
+            want: Ok(value!({
+                "foo[bar": "1",
+            })),
+            tdef: TypeDef::object(query_inner_kind(&BTreeMap::new(), true)),
+        }
+
+        nested_key_deeper_than_segment_cap_falls_back_to_literal {
+            args: func_args![
+                value: value!("foo[a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a]=1"),
+                nested: true,
+            ],
+            want: Ok(value!({
+                "foo[a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a][a]": "1",
+            })),
+            tdef: TypeDef::object(query_inner_kind(&BTreeMap::new(), true)),
+        }
+
+        nested_conflicting_scalar_and_object_keys_last_write_wins {
+            args: func_args![value: value!("foo=1&foo[bar]=2"), nested: true],
+            want: Ok(value!({
+                foo: { bar: "2" },
+            })),
+            tdef: TypeDef::object(query_inner_kind(&BTreeMap::new(), true)),
+        }
+
+        nested_conflicting_object_and_scalar_keys_last_write_wins {
+            args: func_args![value: value!("foo[bar]=2&foo=1"), nested: true],
+            want: Ok(value!({
+                foo: "1",
+            })),
+            tdef: TypeDef::object(query_inner_kind(&BTreeMap::new(), true)),
+        }
+
+        nested_types_matches_leaf_field_name {
+            args: func_args![
+                value: value!("user[age]=3"),
+                types: value!({ age: "int" }),
+                nested: true,
+            ],
+            want: Ok(value!({
+                user: { age: 3 },
+            })),
+            tdef: TypeDef::object(query_inner_kind(&test_types(&[("age", "int")]), true)).with_fallibility(true),
         }
     ];
 }
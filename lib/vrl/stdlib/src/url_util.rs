@@ -0,0 +1,335 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use url::form_urlencoded;
+use vrl::prelude::*;
+
+/// A per-field type coercion applied to the otherwise-`Bytes` values produced
+/// by `parse_query_string`, given via its `types` argument.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Conversion {
+    Bytes,
+    Integer,
+    Float,
+    Boolean,
+    Timestamp,
+    TimestampFmt(String),
+    TimestampTzFmt(String, String),
+}
+
+impl FromStr for Conversion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bytes" | "string" => Ok(Conversion::Bytes),
+            "int" | "integer" => Ok(Conversion::Integer),
+            "float" => Ok(Conversion::Float),
+            "bool" | "boolean" => Ok(Conversion::Boolean),
+            "timestamp" => Ok(Conversion::Timestamp),
+            _ if s.starts_with("timestamp|") => {
+                let mut parts = s.splitn(3, '|');
+                parts.next(); // "timestamp"
+                let format = parts
+                    .next()
+                    .filter(|format| !format.is_empty())
+                    .ok_or_else(|| format!("invalid timestamp conversion: {s:?}"))?;
+
+                match parts.next() {
+                    Some(tz) => Ok(Conversion::TimestampTzFmt(format.to_owned(), tz.to_owned())),
+                    None => Ok(Conversion::TimestampFmt(format.to_owned())),
+                }
+            }
+            _ => Err(format!("unknown conversion type: {s:?}")),
+        }
+    }
+}
+
+impl Conversion {
+    /// The `Kind` a successful conversion produces, used to widen the
+    /// function's `type_def` when `types` is supplied.
+    pub(crate) fn kind(&self) -> Kind {
+        match self {
+            Conversion::Bytes => Kind::bytes(),
+            Conversion::Integer => Kind::integer(),
+            Conversion::Float => Kind::float(),
+            Conversion::Boolean => Kind::boolean(),
+            Conversion::Timestamp
+            | Conversion::TimestampFmt(_)
+            | Conversion::TimestampTzFmt(_, _) => Kind::timestamp(),
+        }
+    }
+
+    /// Converts a single decoded query-string value, naming the offending
+    /// field in any error so callers can point at the failing input.
+    pub(crate) fn convert(&self, field: &str, value: &str) -> Result<Value, ExpressionError> {
+        let err = |message: String| -> ExpressionError {
+            format!("unable to coerce field `{field}` to `{}`: {message}", self.type_name()).into()
+        };
+
+        match self {
+            Conversion::Bytes => Ok(value.into()),
+            Conversion::Integer => value
+                .parse::<i64>()
+                .map(Value::from)
+                .map_err(|error| err(error.to_string())),
+            Conversion::Float => value
+                .parse::<f64>()
+                .map_err(|error| err(error.to_string()))
+                .and_then(|f| {
+                    NotNan::new(f)
+                        .map(Value::Float)
+                        .map_err(|_| err("value is not a number".to_owned()))
+                }),
+            Conversion::Boolean => match value {
+                "true" | "1" => Ok(true.into()),
+                "false" | "0" => Ok(false.into()),
+                _ => Err(err("expected a boolean value".to_owned())),
+            },
+            Conversion::Timestamp => DateTime::parse_from_rfc3339(value)
+                .map(|dt| Value::Timestamp(dt.with_timezone(&Utc)))
+                .map_err(|error| err(error.to_string())),
+            Conversion::TimestampFmt(format) => Utc
+                .datetime_from_str(value, format)
+                .map(Value::Timestamp)
+                .map_err(|error| err(error.to_string())),
+            Conversion::TimestampTzFmt(format, tz) => {
+                let tz: Tz = tz.parse().map_err(|_| err(format!("unknown timezone {tz:?}")))?;
+                let naive = chrono::NaiveDateTime::parse_from_str(value, format)
+                    .map_err(|error| err(error.to_string()))?;
+
+                tz.from_local_datetime(&naive)
+                    .single()
+                    .map(|dt| Value::Timestamp(dt.with_timezone(&Utc)))
+                    .ok_or_else(|| err("ambiguous or invalid local time".to_owned()))
+            }
+        }
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            Conversion::Bytes => "string",
+            Conversion::Integer => "integer",
+            Conversion::Float => "float",
+            Conversion::Boolean => "boolean",
+            Conversion::Timestamp
+            | Conversion::TimestampFmt(_)
+            | Conversion::TimestampTzFmt(_, _) => "timestamp",
+        }
+    }
+}
+
+/// One segment of a PHP/Rails-style bracket path, e.g. `foo[bar][]` decodes
+/// to `[Key("foo"), Key("bar"), Push]`.
+#[derive(Debug)]
+enum Segment {
+    Key(String),
+    Index(usize),
+    Push,
+}
+
+/// Bracket-notation nesting depth is attacker/log-controlled input just like
+/// an individual index (see `MAX_INDEX`); a key with more segments than this
+/// is treated the same as a malformed key, so a key like
+/// `a[b][c][d]...` repeated thousands of brackets deep can't force
+/// thousand-frame recursion through `set_path`.
+const MAX_SEGMENTS: usize = 32;
+
+/// Decodes a bracket-notation key into its path segments. Returns `None` for
+/// a plain key (no brackets at all), a malformed one (unbalanced or trailing
+/// characters after a closing bracket), or one nested deeper than
+/// `MAX_SEGMENTS`, in which case the caller falls back to treating the raw
+/// key literally.
+fn parse_bracket_segments(key: &str) -> Option<Vec<Segment>> {
+    let open = key.find('[')?;
+    let (head, mut rest) = key.split_at(open);
+    if head.is_empty() {
+        return None;
+    }
+
+    let mut segments = vec![Segment::Key(head.to_owned())];
+    while !rest.is_empty() {
+        if !rest.starts_with('[') {
+            return None;
+        }
+        if segments.len() >= MAX_SEGMENTS {
+            return None;
+        }
+        let close = rest.find(']')?;
+        let content = &rest[1..close];
+        segments.push(match content {
+            "" => Segment::Push,
+            _ => match content.parse::<usize>() {
+                Ok(index) => Segment::Index(index),
+                Err(_) => Segment::Key(content.to_owned()),
+            },
+        });
+        rest = &rest[close + 1..];
+    }
+
+    Some(segments)
+}
+
+/// Bracket-notation indices are attacker/log-controlled input; an index
+/// beyond this bound is not grown into: `set_path` appends one element
+/// instead, the same as a `[]` push segment, so a key like
+/// `a[999999999999]` can't be used to force a multi-gigabyte allocation.
+const MAX_INDEX: usize = 1024;
+
+/// Writes `value` at `segments` within `target`, creating intermediate
+/// objects/arrays as needed. A segment that conflicts with the existing
+/// shape (e.g. indexing into a scalar) overwrites it: last write wins.
+fn set_path(target: &mut Value, segments: &[Segment], value: Value) {
+    match segments.split_first() {
+        None => *target = value,
+        Some((Segment::Key(key), rest)) => {
+            if !matches!(target, Value::Object(_)) {
+                *target = Value::Object(BTreeMap::new());
+            }
+            if let Value::Object(map) = target {
+                set_path(map.entry(key.clone()).or_insert(Value::Null), rest, value);
+            }
+        }
+        Some((Segment::Index(index), rest)) if *index > MAX_INDEX => {
+            // Out of range: fall back to appending one element, same as a
+            // `[]` push segment, rather than trusting the attacker-supplied
+            // index to size an allocation or as an arithmetic operand.
+            if !matches!(target, Value::Array(_)) {
+                *target = Value::Array(Vec::new());
+            }
+            if let Value::Array(array) = target {
+                let mut slot = Value::Null;
+                set_path(&mut slot, rest, value);
+                array.push(slot);
+            }
+        }
+        Some((Segment::Index(index), rest)) => {
+            if !matches!(target, Value::Array(_)) {
+                *target = Value::Array(Vec::new());
+            }
+            if let Value::Array(array) = target {
+                let len = array.len().max(index.saturating_add(1));
+                if array.len() < len {
+                    array.resize(len, Value::Null);
+                }
+                set_path(&mut array[*index], rest, value);
+            }
+        }
+        Some((Segment::Push, rest)) => {
+            if !matches!(target, Value::Array(_)) {
+                *target = Value::Array(Vec::new());
+            }
+            if let Value::Array(array) = target {
+                let mut slot = Value::Null;
+                set_path(&mut slot, rest, value);
+                array.push(slot);
+            }
+        }
+    }
+}
+
+/// Merges `value` into `map` under `key`, flattening repeated keys into an
+/// array (the default, non-nested behavior).
+fn insert_flat(map: &mut BTreeMap<String, Value>, key: String, value: Value) {
+    match map.get_mut(&key) {
+        Some(Value::Array(array)) => array.push(value),
+        // An object at this key can only have come from nested bracket
+        // decoding (`insert_flat` itself never builds one); a later
+        // bracket-less write to the same key overwrites it wholesale,
+        // consistent with the reverse order (bracket key after scalar),
+        // rather than array-wrapping an object next to a scalar.
+        Some(existing @ Value::Object(_)) => *existing = value,
+        Some(existing) => {
+            let previous = std::mem::replace(existing, Value::Null);
+            *existing = Value::Array(vec![previous, value]);
+        }
+        None => {
+            map.insert(key, value);
+        }
+    }
+}
+
+/// Flattens a decoded `form_urlencoded` query into a `Value::Object`,
+/// applying any `types` coercion.
+///
+/// By default, repeated keys collapse into arrays. When `nested` is set,
+/// keys are additionally decoded as PHP/Rails-style bracket paths
+/// (`foo[bar][]=baz`) into a nested object/array shape; a key that isn't
+/// valid bracket notation falls back to the flat behavior. `types` is
+/// matched against the *leaf* field name a bracket path resolves to (e.g.
+/// `user[age]` is looked up as `age`, not `"user[age]"`), so the two
+/// arguments compose instead of `types` silently only ever matching
+/// bracket-less keys.
+pub(crate) fn parse_query(
+    query: form_urlencoded::Parse,
+    types: &BTreeMap<String, Conversion>,
+    nested: bool,
+) -> Result<BTreeMap<String, Value>, ExpressionError> {
+    let mut root = Value::Object(BTreeMap::new());
+
+    for (key, raw_value) in query {
+        let key = key.into_owned();
+        let segments = nested.then(|| parse_bracket_segments(&key)).flatten();
+
+        let lookup_key = segments
+            .as_ref()
+            .and_then(|segments| {
+                segments.iter().rev().find_map(|segment| match segment {
+                    Segment::Key(key) => Some(key.as_str()),
+                    Segment::Index(_) | Segment::Push => None,
+                })
+            })
+            .unwrap_or(key.as_str());
+
+        let value = match types.get(lookup_key) {
+            Some(conversion) => conversion.convert(&key, raw_value.as_ref())?,
+            None => raw_value.as_ref().into(),
+        };
+
+        match (segments, &mut root) {
+            (Some(segments), root) => set_path(root, &segments, value),
+            (None, Value::Object(map)) => insert_flat(map, key, value),
+            (None, _) => unreachable!("root is always initialized as an object"),
+        }
+    }
+
+    match root {
+        Value::Object(map) => Ok(map),
+        _ => unreachable!("root is always initialized as an object"),
+    }
+}
+
+/// The `Kind` of each value in the object `parse_query_string` returns.
+///
+/// The scalar kind always includes `bytes` (untyped, or unlisted, fields
+/// come through as the raw decoded string) plus the `Kind` each distinct
+/// `Conversion` in `types` actually produces, via `Conversion::kind`, so a
+/// `types` argument that only ever converts to `int` doesn't also widen
+/// the result to include `float`/`boolean`/`timestamp`. A value may also
+/// be an array (repeated keys collapse into one), and `nested`
+/// additionally allows a value to be an object, reflecting the
+/// bracket-notation nesting `nested: true` decodes.
+///
+/// This is a safe superset, not a true recursive type: a value nested two
+/// or more levels deep (e.g. `a[b][c]=x`) is only known to be "some array
+/// or object" rather than having its own precisely-typed contents, since
+/// `Collection::any()` doesn't thread back through `query_inner_kind`
+/// itself. Getting a precise recursive `Kind` here would need the kind
+/// system to express that directly; this trades that precision for a
+/// kind that's still guaranteed to cover every shape `parse_query` can
+/// actually produce.
+pub(crate) fn query_inner_kind(types: &BTreeMap<String, Conversion>, nested: bool) -> Collection<Field> {
+    let scalar = types
+        .values()
+        .map(Conversion::kind)
+        .fold(Kind::bytes(), Kind::union);
+
+    let mut kind = scalar.or_array(Collection::any());
+    if nested {
+        kind = kind.or_object(Collection::any());
+    }
+
+    Collection::from_unknown(kind)
+}
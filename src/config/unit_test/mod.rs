@@ -0,0 +1,30 @@
+mod unit_test_components;
+
+pub use unit_test_components::{
+    build_unit_test_case, TestFilter, TestRunSummary, TestRunner, TestRunnerOptions, UnitTestCase,
+    UnitTestCaseSummary, UnitTestSink, UnitTestSinkCheck, UnitTestSinkConfig, UnitTestSinkResult,
+    UnitTestSourceConfig,
+};
+
+/// Entry point for the `vector test` subcommand: runs `tests` under
+/// `options`, printing a pass/fail line (and any failure detail) for each,
+/// and returns the process exit code the subcommand should report.
+pub async fn run_tests(tests: Vec<UnitTestCase>, options: TestRunnerOptions) -> i32 {
+    let runner = TestRunner::new(options);
+    let summary = runner.run(tests).await;
+
+    for result in &summary.results {
+        if result.passed {
+            println!("test {} ... ok", result.test_name);
+        } else {
+            println!("test {} ... failed", result.test_name);
+            for error in &result.test_errors {
+                println!("  {error}");
+            }
+        }
+    }
+
+    println!("{} passed, {} failed", summary.passed, summary.failed);
+
+    summary.exit_code()
+}
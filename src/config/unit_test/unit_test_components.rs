@@ -1,13 +1,20 @@
+use std::collections::BTreeMap;
+use std::pin::Pin;
 use std::sync::Arc;
 
-use futures_util::{future, stream::BoxStream, FutureExt, StreamExt};
+use futures::Future;
+use futures_util::{
+    future,
+    stream::{self, BoxStream},
+    FutureExt, StreamExt,
+};
 use serde::{Deserialize, Serialize};
 use tokio::sync::{oneshot, Mutex};
 use vector_config::configurable_component;
 use vector_core::config::LogNamespace;
 use vector_core::{
     config::{DataType, Input, Output},
-    event::Event,
+    event::{Event, LogEvent, Value},
     sink::{StreamSink, VectorSink},
 };
 
@@ -57,6 +64,10 @@ pub enum UnitTestSinkCheck {
     /// Check all events that are received against the list of conditions.
     Checks(Vec<Vec<Condition>>),
 
+    /// Compare the received events against an expected set, reporting a
+    /// field-level diff rather than a full re-dump of every event.
+    Assert(Vec<Event>),
+
     /// Check that no events were received.
     NoOutputs,
 
@@ -70,6 +81,18 @@ impl Default for UnitTestSinkCheck {
     }
 }
 
+impl UnitTestSinkCheck {
+    /// Builds a check that diffs the sink's received events against
+    /// `expected`, field by field, rather than running conditions against
+    /// them. A test definition that specifies expected output events
+    /// instead of conditions is turned into this before being attached to
+    /// the `UnitTestSinkConfig` for that transform/branch.
+    #[must_use]
+    pub fn assert(expected: Vec<Event>) -> Self {
+        UnitTestSinkCheck::Assert(expected)
+    }
+}
+
 #[derive(Debug)]
 pub struct UnitTestSinkResult {
     pub test_name: String,
@@ -156,20 +179,40 @@ impl StreamSink<Event> for UnitTestSink {
                     for (i, check) in checks.iter().enumerate() {
                         let mut check_errors = Vec::new();
                         for (j, condition) in check.iter().enumerate() {
-                            let mut condition_errors = Vec::new();
-                            for event in output_events.iter() {
+                            // The event whose condition error is shortest is treated as the
+                            // closest match, since a condition that almost passes tends to
+                            // fail with the least to say about it.
+                            let mut closest: Option<(usize, String)> = None;
+                            let mut passed = false;
+
+                            for (event_index, event) in output_events.iter().enumerate() {
                                 match condition.check_with_context(event.clone()).0 {
                                     Ok(_) => {
-                                        condition_errors.clear();
+                                        passed = true;
                                         break;
                                     }
                                     Err(error) => {
-                                        condition_errors
-                                            .push(format!("  condition[{}]: {}", j, error));
+                                        if closest
+                                            .as_ref()
+                                            .map_or(true, |(_, prev)| error.len() < prev.len())
+                                        {
+                                            closest = Some((event_index, error));
+                                        }
                                     }
                                 }
                             }
-                            check_errors.extend(condition_errors);
+
+                            if !passed {
+                                if let Some((event_index, error)) = closest {
+                                    check_errors.push(format!(
+                                        "  condition[{j}] failed: {error} (closest match: event[{event_index}])"
+                                    ));
+                                    check_errors.push(format!(
+                                        "    event[{event_index}]: {}",
+                                        events_to_string(&output_events[event_index..=event_index])
+                                    ));
+                                }
+                            }
                         }
                         // If there are errors, add a preamble to the output
                         if !check_errors.is_empty() {
@@ -184,14 +227,27 @@ impl StreamSink<Event> for UnitTestSink {
 
                         result.test_errors.extend(check_errors);
                     }
+                }
+            }
+            UnitTestSinkCheck::Assert(expected) => {
+                if expected.len() != output_events.len() {
+                    result.test_errors.push(format!(
+                        "assertion for transforms {:?} failed: expected {} event(s), received {}",
+                        self.transform_ids,
+                        expected.len(),
+                        output_events.len()
+                    ));
+                }
 
-                    // If there are errors, add a summary of events received
-                    if !result.test_errors.is_empty() {
-                        result.test_errors.push(format!(
-                            "output payloads from {:?} (events encoded as JSON):\n  {}",
-                            self.transform_ids,
-                            events_to_string(&output_events)
-                        ));
+                for (i, (expected_event, actual_event)) in
+                    expected.iter().zip(output_events.iter()).enumerate()
+                {
+                    let diff = diff_events(expected_event, actual_event);
+                    if !diff.is_empty() {
+                        result.test_errors.push(format!("  event[{i}] mismatch:"));
+                        result
+                            .test_errors
+                            .extend(diff.into_iter().map(|line| format!("    {line}")));
                     }
                 }
             }
@@ -230,3 +286,474 @@ fn events_to_string(events: &[Event]) -> String {
         .collect::<Vec<_>>()
         .join("\n  ")
 }
+
+/// Compares two log events field by field, returning a compact list of
+/// `added`/`removed`/`changed` lines rather than a full JSON re-dump.
+/// Non-log events (metrics, traces) are compared wholesale.
+fn diff_events(expected: &Event, actual: &Event) -> Vec<String> {
+    let (Event::Log(expected), Event::Log(actual)) = (expected, actual) else {
+        return if expected == actual {
+            Vec::new()
+        } else {
+            vec!["events differ".to_string()]
+        };
+    };
+
+    let expected_fields: BTreeMap<String, Value> = expected
+        .all_fields()
+        .into_iter()
+        .flatten()
+        .map(|(key, value)| (key, value.clone()))
+        .collect();
+    let actual_fields: BTreeMap<String, Value> = actual
+        .all_fields()
+        .into_iter()
+        .flatten()
+        .map(|(key, value)| (key, value.clone()))
+        .collect();
+
+    let mut diff = Vec::new();
+    for (key, expected_value) in &expected_fields {
+        match actual_fields.get(key) {
+            None => diff.push(format!("removed `{key}`: {expected_value}")),
+            Some(actual_value) if actual_value != expected_value => {
+                diff.push(format!(
+                    "changed `{key}`: {expected_value} -> {actual_value}"
+                ));
+            }
+            _ => {}
+        }
+    }
+    for (key, actual_value) in &actual_fields {
+        if !expected_fields.contains_key(key) {
+            diff.push(format!("added `{key}`: {actual_value}"));
+        }
+    }
+
+    diff
+}
+
+/// A single configured unit test, ready to run: its name and tags for
+/// selection, and the future that builds and drives its topology through to
+/// completion, collecting the `UnitTestSinkResult` of each of its sinks.
+pub struct UnitTestCase {
+    pub name: String,
+    pub tags: Vec<String>,
+    run: Pin<Box<dyn Future<Output = Vec<UnitTestSinkResult>> + Send>>,
+}
+
+impl UnitTestCase {
+    pub fn new(
+        name: String,
+        tags: Vec<String>,
+        run: impl Future<Output = Vec<UnitTestSinkResult>> + Send + 'static,
+    ) -> Self {
+        Self {
+            name,
+            tags,
+            run: Box::pin(run),
+        }
+    }
+}
+
+/// Selects which configured tests a `TestRunner` should run.
+///
+/// `name` matches as a plain substring, or as a glob if it contains a
+/// single `*` wildcard. `tags` matches if the test carries at least one of
+/// the listed tags; an empty list means no tag filtering.
+#[derive(Clone, Debug, Default)]
+pub struct TestFilter {
+    pub name: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl TestFilter {
+    fn matches(&self, test: &UnitTestCase) -> bool {
+        let name_matches = self
+            .name
+            .as_deref()
+            .map_or(true, |pattern| glob_match(pattern, &test.name));
+        let tags_match =
+            self.tags.is_empty() || self.tags.iter().any(|tag| test.tags.contains(tag));
+
+        name_matches && tags_match
+    }
+}
+
+/// Matches `candidate` against `pattern`, treating a single `*` in
+/// `pattern` as a wildcard and otherwise requiring a substring match.
+fn glob_match(pattern: &str, candidate: &str) -> bool {
+    match pattern.split_once('*') {
+        Some((prefix, suffix)) => {
+            candidate.len() >= prefix.len() + suffix.len()
+                && candidate.starts_with(prefix)
+                && candidate.ends_with(suffix)
+        }
+        None => candidate.contains(pattern),
+    }
+}
+
+/// Options controlling a `TestRunner` run.
+#[derive(Clone, Debug)]
+pub struct TestRunnerOptions {
+    pub filter: TestFilter,
+    /// Stop scheduling further tests as soon as one selected test fails.
+    pub fail_fast: bool,
+    /// Maximum number of tests run concurrently.
+    pub max_parallel: usize,
+}
+
+impl Default for TestRunnerOptions {
+    fn default() -> Self {
+        Self {
+            filter: TestFilter::default(),
+            fail_fast: false,
+            max_parallel: 4,
+        }
+    }
+}
+
+/// The machine-readable result of a full test run, meant for CI to consume
+/// (e.g. as JSON) alongside the existing human-oriented `test_errors` text.
+#[derive(Debug, Serialize)]
+pub struct TestRunSummary {
+    pub passed: usize,
+    pub failed: usize,
+    pub results: Vec<UnitTestCaseSummary>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnitTestCaseSummary {
+    pub test_name: String,
+    pub passed: bool,
+    pub test_errors: Vec<String>,
+}
+
+impl TestRunSummary {
+    /// The process exit code `vector test` should report: non-zero as soon
+    /// as any selected test failed.
+    #[must_use]
+    pub fn exit_code(&self) -> i32 {
+        i32::from(self.failed > 0)
+    }
+}
+
+/// Builds a runnable `UnitTestCase` out of one sink per transform/branch
+/// being asserted on, feeding `events` (the test's configured source
+/// output, already routed through the test's transform chain by the
+/// topology builder) into each and collecting the `UnitTestSinkResult`s
+/// they report back over their `result_tx`.
+pub fn build_unit_test_case(
+    name: String,
+    tags: Vec<String>,
+    events: Vec<Event>,
+    sinks: Vec<UnitTestSinkConfig>,
+) -> UnitTestCase {
+    UnitTestCase::new(name, tags, async move {
+        let mut results = Vec::with_capacity(sinks.len());
+
+        for sink_config in sinks {
+            let (result_tx, result_rx) = oneshot::channel();
+            let sink = UnitTestSink {
+                test_name: sink_config.test_name.clone(),
+                transform_ids: sink_config.transform_ids,
+                result_tx: Some(result_tx),
+                check: sink_config.check,
+            };
+
+            let input = stream::iter(events.clone()).boxed();
+            let (run_result, recv_result) =
+                future::join(Box::new(sink).run(input), result_rx).await;
+
+            results.push(match (run_result, recv_result) {
+                (Ok(()), Ok(result)) => result,
+                _ => UnitTestSinkResult {
+                    test_name: sink_config.test_name,
+                    test_errors: vec!["unit test sink failed to produce a result".to_string()],
+                },
+            });
+        }
+
+        results
+    })
+}
+
+/// Runs a set of configured unit tests, applying a `TestFilter`, bounding
+/// concurrency across independent tests, and aggregating their
+/// `UnitTestSinkResult`s into a `TestRunSummary`. Driven by
+/// [`super::run_tests`], the `vector test` subcommand's entry point.
+pub struct TestRunner {
+    options: TestRunnerOptions,
+}
+
+impl TestRunner {
+    pub fn new(options: TestRunnerOptions) -> Self {
+        Self { options }
+    }
+
+    pub async fn run(&self, tests: Vec<UnitTestCase>) -> TestRunSummary {
+        let selected = tests
+            .into_iter()
+            .filter(|test| self.options.filter.matches(test));
+
+        let max_parallel = self.options.max_parallel.max(1);
+        let mut runs = stream::iter(selected.map(|test| async move {
+            let UnitTestCase { name, run, .. } = test;
+            let sink_results = run.await;
+            let test_errors: Vec<String> = sink_results
+                .into_iter()
+                .flat_map(|result| result.test_errors)
+                .collect();
+
+            UnitTestCaseSummary {
+                test_name: name,
+                passed: test_errors.is_empty(),
+                test_errors,
+            }
+        }))
+        .buffer_unordered(max_parallel);
+
+        let mut results = Vec::new();
+        while let Some(summary) = runs.next().await {
+            let failed = !summary.passed;
+            results.push(summary);
+
+            if failed && self.options.fail_fast {
+                break;
+            }
+        }
+
+        let passed = results.iter().filter(|summary| summary.passed).count();
+        let failed = results.len() - passed;
+
+        TestRunSummary {
+            passed,
+            failed,
+            results,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn glob_match_plain_substring() {
+        assert!(glob_match("bar", "foobarbaz"));
+        assert!(!glob_match("qux", "foobarbaz"));
+    }
+
+    #[test]
+    fn glob_match_wildcard() {
+        assert!(glob_match("foo*baz", "foobarbaz"));
+        assert!(glob_match("foo*", "foobarbaz"));
+        assert!(glob_match("*baz", "foobarbaz"));
+        assert!(!glob_match("foo*qux", "foobarbaz"));
+    }
+
+    fn test_case(name: &str, tags: &[&str]) -> UnitTestCase {
+        UnitTestCase::new(
+            name.to_string(),
+            tags.iter().map(|tag| tag.to_string()).collect(),
+            async { Vec::new() },
+        )
+    }
+
+    #[test]
+    fn test_filter_matches_by_name() {
+        let filter = TestFilter {
+            name: Some("foo*".to_string()),
+            tags: Vec::new(),
+        };
+
+        assert!(filter.matches(&test_case("foobar", &[])));
+        assert!(!filter.matches(&test_case("barfoo", &[])));
+    }
+
+    #[test]
+    fn test_filter_matches_by_tags() {
+        let filter = TestFilter {
+            name: None,
+            tags: vec!["smoke".to_string()],
+        };
+
+        assert!(filter.matches(&test_case("anything", &["smoke", "slow"])));
+        assert!(!filter.matches(&test_case("anything", &["slow"])));
+    }
+
+    #[test]
+    fn test_filter_empty_matches_everything() {
+        let filter = TestFilter::default();
+        assert!(filter.matches(&test_case("anything", &["any-tag"])));
+    }
+
+    #[tokio::test]
+    async fn test_runner_respects_max_parallel() {
+        let concurrent = Arc::new(AtomicUsize::new(0));
+        let max_seen = Arc::new(AtomicUsize::new(0));
+
+        let tests = (0..6)
+            .map(|i| {
+                let concurrent = Arc::clone(&concurrent);
+                let max_seen = Arc::clone(&max_seen);
+                UnitTestCase::new(format!("test-{i}"), Vec::new(), async move {
+                    let current = concurrent.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_seen.fetch_max(current, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    concurrent.fetch_sub(1, Ordering::SeqCst);
+                    Vec::new()
+                })
+            })
+            .collect();
+
+        let runner = TestRunner::new(TestRunnerOptions {
+            max_parallel: 2,
+            ..Default::default()
+        });
+        let summary = runner.run(tests).await;
+
+        assert_eq!(summary.passed, 6);
+        assert!(max_seen.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[tokio::test]
+    async fn test_runner_fail_fast_stops_scheduling_further_tests() {
+        let tests = vec![
+            UnitTestCase::new("ok".to_string(), Vec::new(), async { Vec::new() }),
+            UnitTestCase::new("fails".to_string(), Vec::new(), async {
+                vec![UnitTestSinkResult {
+                    test_name: "fails".to_string(),
+                    test_errors: vec!["boom".to_string()],
+                }]
+            }),
+            UnitTestCase::new("never-run".to_string(), Vec::new(), async { Vec::new() }),
+        ];
+
+        let runner = TestRunner::new(TestRunnerOptions {
+            max_parallel: 1,
+            fail_fast: true,
+            ..Default::default()
+        });
+        let summary = runner.run(tests).await;
+
+        assert_eq!(summary.results.len(), 2);
+        assert_eq!(summary.passed, 1);
+        assert_eq!(summary.failed, 1);
+    }
+
+    #[tokio::test]
+    async fn test_runner_applies_filter() {
+        let tests = vec![test_case("alpha", &["keep"]), test_case("beta", &["drop"])];
+
+        let runner = TestRunner::new(TestRunnerOptions {
+            filter: TestFilter {
+                name: None,
+                tags: vec!["keep".to_string()],
+            },
+            ..Default::default()
+        });
+        let summary = runner.run(tests).await;
+
+        assert_eq!(summary.results.len(), 1);
+        assert_eq!(summary.results[0].test_name, "alpha");
+    }
+
+    #[tokio::test]
+    async fn build_unit_test_case_drives_sinks_and_collects_results() {
+        let sink_config = UnitTestSinkConfig {
+            test_name: "my test".to_string(),
+            transform_ids: vec!["my transform".to_string()],
+            check: UnitTestSinkCheck::NoOutputs,
+            ..Default::default()
+        };
+
+        let test = build_unit_test_case(
+            "my test".to_string(),
+            Vec::new(),
+            Vec::new(),
+            vec![sink_config],
+        );
+
+        let runner = TestRunner::new(TestRunnerOptions::default());
+        let summary = runner.run(vec![test]).await;
+
+        assert_eq!(summary.passed, 1);
+        assert_eq!(summary.exit_code(), 0);
+    }
+
+    #[tokio::test]
+    async fn build_unit_test_case_reports_failure_through_exit_code() {
+        let sink_config = UnitTestSinkConfig {
+            test_name: "my test".to_string(),
+            check: UnitTestSinkCheck::NoOutputs,
+            ..Default::default()
+        };
+
+        let events = vec![Event::Log(LogEvent::default())];
+        let test =
+            build_unit_test_case("my test".to_string(), Vec::new(), events, vec![sink_config]);
+
+        let runner = TestRunner::new(TestRunnerOptions::default());
+        let summary = runner.run(vec![test]).await;
+
+        assert_eq!(summary.failed, 1);
+        assert_eq!(summary.exit_code(), 1);
+    }
+
+    fn log_event(fields: &[(&str, &str)]) -> Event {
+        let mut log = LogEvent::default();
+        for (key, value) in fields {
+            log.insert(*key, *value);
+        }
+        Event::Log(log)
+    }
+
+    #[test]
+    fn diff_events_reports_no_diff_for_identical_events() {
+        let event = log_event(&[("foo", "bar")]);
+        assert!(diff_events(&event, &event).is_empty());
+    }
+
+    #[test]
+    fn diff_events_reports_added_removed_and_changed_fields() {
+        let expected = log_event(&[("kept", "same"), ("removed", "gone"), ("changed", "old")]);
+        let actual = log_event(&[("kept", "same"), ("changed", "new"), ("added", "new-field")]);
+
+        let diff = diff_events(&expected, &actual);
+
+        assert!(diff.iter().any(|line| line.contains("removed `removed`")));
+        assert!(diff.iter().any(|line| line.contains("changed `changed`")
+            && line.contains("old")
+            && line.contains("new")));
+        assert!(diff.iter().any(|line| line.contains("added `added`")));
+        assert!(!diff.iter().any(|line| line.contains("kept")));
+    }
+
+    #[tokio::test]
+    async fn assert_check_reports_a_diff_for_each_mismatched_event() {
+        let expected = vec![log_event(&[("message", "hello")])];
+        let actual = vec![log_event(&[("message", "goodbye")])];
+
+        let sink_config = UnitTestSinkConfig {
+            test_name: "my test".to_string(),
+            check: UnitTestSinkCheck::assert(expected),
+            ..Default::default()
+        };
+
+        let test =
+            build_unit_test_case("my test".to_string(), Vec::new(), actual, vec![sink_config]);
+        let runner = TestRunner::new(TestRunnerOptions::default());
+        let summary = runner.run(vec![test]).await;
+
+        assert_eq!(summary.failed, 1);
+        assert!(summary.results[0]
+            .test_errors
+            .iter()
+            .any(|line| line.contains("changed `message`")));
+    }
+}